@@ -0,0 +1,62 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use rlox::lexer::{Token, TokenKind, tokenize};
+
+const ITERATIONS: usize = 20;
+
+fn source() -> String {
+    let chunk = r#"fun fib(n) {
+  if (n < 2) return n; // base case
+  return fib(n - 1) + fib(n - 2);
+}
+
+var greeting = "hello, world";
+print fib(8);
+"#;
+    chunk.repeat(5_000)
+}
+
+fn keyword_source() -> String {
+    let chunk = "and class const else enum false for fun if is nil or print repeat return \
+                 super this true var while andy classy format iffy\n";
+    chunk.repeat(10_000)
+}
+
+fn bench(name: &str, source: &str, f: impl Fn(&str) -> usize) {
+    let start = Instant::now();
+    let mut count = 0;
+    for _ in 0..ITERATIONS {
+        count = black_box(f(black_box(source)));
+    }
+    let elapsed = start.elapsed() / ITERATIONS as u32;
+    println!("{name:<24} {count:>8} tokens {elapsed:>12.2?}/iter");
+}
+
+fn main() {
+    let source = source();
+    let keyword_source = keyword_source();
+
+    bench("borrowed tokens", &source, |source| {
+        tokenize(source).fold(0, |count, token| {
+            black_box(token.node);
+            count + 1
+        })
+    });
+    bench("owned tokens", &source, |source| {
+        tokenize(source).fold(0, |count, token| {
+            black_box(token.node.to_owned());
+            count + 1
+        })
+    });
+    bench("identifiers only", &source, |source| {
+        tokenize(source)
+            .filter(|token| matches!(token.node, Token::Ident(_)))
+            .count()
+    });
+    bench("keyword dense", &keyword_source, |source| {
+        tokenize(source)
+            .filter(|token| token.node.kind() != TokenKind::Ident)
+            .count()
+    });
+}
@@ -0,0 +1,25 @@
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy)]
+pub enum Color {
+    Red,
+    Cyan,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[1;31m",
+            Color::Cyan => "\x1b[36m",
+        }
+    }
+}
+
+/// Wraps `text` in the ANSI escape codes for `color`, if `enabled`
+pub fn paint(text: &str, color: Color, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}{}", color.code(), text, RESET)
+    } else {
+        text.to_string()
+    }
+}
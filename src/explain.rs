@@ -0,0 +1,72 @@
+/// Longer explanations of the error codes reported by diagnostics
+static EXPLANATIONS: [(&str, &str); 4] = [
+    (
+        "E0001",
+        "\
+The input ended, or a character appeared, where the lexer expected something else.
+
+This is reported for a string or block comment that is never closed, and for an
+`@` that is not followed by an annotation name.
+
+Erroneous example:
+
+    print \"hello;
+
+Close the string with a matching quote:
+
+    print \"hello\";
+",
+    ),
+    (
+        "E0002",
+        "\
+A character that does not start any token was found.
+
+Erroneous example:
+
+    var a = 1 # 2;
+
+Remove the character, or put it inside a string or a comment:
+
+    var a = 1; // 2
+",
+    ),
+    (
+        "E0003",
+        "\
+A number literal is not valid.
+
+With strict numbers, integer literals may not have leading zeros.
+
+Erroneous example:
+
+    var mode = 0755;
+
+Remove the leading zeros:
+
+    var mode = 755;
+",
+    ),
+    (
+        "E0004",
+        "\
+A string or number literal is longer than the configured limit.
+
+Erroneous example, with strings limited to 3 characters:
+
+    print \"hello\";
+
+Shorten the literal, or raise the limit:
+
+    print \"hi\";
+",
+    ),
+];
+
+/// Explanation of `code`, if it is a known error code
+pub fn explanation(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, text)| *text)
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+/// Bounded in-memory list of input lines, oldest first
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<String>,
+    size: usize,
+}
+
+impl History {
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size,
+        }
+    }
+
+    /// Appends a line, evicting the oldest entries beyond the size limit
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if self.size == 0 || line.trim().is_empty() {
+            return;
+        }
+
+        while self.entries.len() >= self.size {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(line.to_string());
+    }
+
+    /// Entry at `index`, counting from the oldest retained one
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Most recent entry starting with `prefix`
+    pub fn search(&self, prefix: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.starts_with(prefix))
+            .map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_oldest_beyond_size() {
+        let mut history = History::new(2);
+        history.push("var a = 1;\n");
+        history.push("var b = 2;\n");
+        history.push("var c = 3;\n");
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0), Some("var b = 2;"));
+        assert_eq!(history.get(1), Some("var c = 3;"));
+        assert_eq!(history.get(2), None);
+    }
+
+    #[test]
+    fn search_returns_most_recent_match() {
+        let mut history = History::new(10);
+        history.push("print a;");
+        history.push("var a = 1;");
+        history.push("print b;");
+        history.push("var b = 2;");
+
+        assert_eq!(history.search("print"), Some("print b;"));
+        assert_eq!(history.search("var a"), Some("var a = 1;"));
+        assert_eq!(history.search("fun"), None);
+    }
+
+    #[test]
+    fn huge_size_does_not_preallocate() {
+        let mut history = History::new(usize::MAX);
+        history.push("print 1;");
+
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.get(0), Some("print 1;"));
+    }
+
+    #[test]
+    fn ignores_blank_lines_and_zero_size() {
+        let mut history = History::new(10);
+        history.push("\n");
+        history.push("   ");
+        assert!(history.is_empty());
+
+        let mut disabled = History::new(0);
+        disabled.push("print 1;");
+        assert!(disabled.is_empty());
+    }
+}
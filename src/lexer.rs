@@ -1,13 +1,16 @@
-use std::iter::Peekable;
+use std::borrow::Cow;
+use std::iter::{FusedIterator, Peekable};
 use std::str::Chars;
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
     // Singe-character tokens
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -15,6 +18,8 @@ pub enum Token {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
 
     // One or two character tokens
     Bang,
@@ -26,75 +31,513 @@ pub enum Token {
     Less,
     LessEqual,
 
+    // Three character tokens
+    Spread,
+
+    // Literlas
+    Ident(Cow<'a, str>),
+    Annotation(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Number {
+        value: f64,
+        is_int: bool,
+    },
+
+    // Keywords
+    And,
+    Break,
+    Catch,
+    Class,
+    Const,
+    Continue,
+    Defer,
+    Else,
+    Enum,
+    False,
+    Finally,
+    Fun,
+    For,
+    If,
+    In,
+    Is,
+    Nil,
+    Or,
+    Print,
+    Repeat,
+    Super,
+    Return,
+    This,
+    Throw,
+    True,
+    Try,
+    Var,
+    While,
+    With,
+
+    Eof,
+    Unknown,
+    Unexpected {
+        line: usize,
+        col: usize,
+    },
+    InvalidNumber,
+    LiteralTooLong,
+
+    // Meaningless lexemes
+    Comment {
+        text: Cow<'a, str>,
+        kind: CommentKind,
+    },
+    Whitespace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TokenKind {
+    // Singe-character tokens
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Dot,
+    Minus,
+    Plus,
+    Semicolon,
+    Slash,
+    Star,
+    Question,
+    Colon,
+
+    // One or two character tokens
+    Bang,
+    BangEqual,
+    Equal,
+    EqualEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+
+    // Three character tokens
+    Spread,
+
     // Literlas
-    Ident(String),
-    String(String),
-    Number(f64),
+    Ident,
+    Annotation,
+    String,
+    Number,
 
     // Keywords
     And,
+    Break,
+    Catch,
     Class,
+    Const,
+    Continue,
+    Defer,
     Else,
+    Enum,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
+    Is,
     Nil,
     Or,
     Print,
+    Repeat,
     Super,
     Return,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
+    With,
 
     Eof,
     Unknown,
-    Unexpected { line: usize, col: usize },
+    Unexpected,
+    InvalidNumber,
+    LiteralTooLong,
 
     // Meaningless lexemes
-    Comment(String),
+    Comment,
     Whitespace,
 }
 
+impl Token<'_> {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::LeftParen => TokenKind::LeftParen,
+            Token::RightParen => TokenKind::RightParen,
+            Token::LeftBrace => TokenKind::LeftBrace,
+            Token::RightBrace => TokenKind::RightBrace,
+            Token::LeftBracket => TokenKind::LeftBracket,
+            Token::RightBracket => TokenKind::RightBracket,
+            Token::Comma => TokenKind::Comma,
+            Token::Dot => TokenKind::Dot,
+            Token::Minus => TokenKind::Minus,
+            Token::Plus => TokenKind::Plus,
+            Token::Semicolon => TokenKind::Semicolon,
+            Token::Slash => TokenKind::Slash,
+            Token::Star => TokenKind::Star,
+            Token::Question => TokenKind::Question,
+            Token::Colon => TokenKind::Colon,
+            Token::Bang => TokenKind::Bang,
+            Token::BangEqual => TokenKind::BangEqual,
+            Token::Equal => TokenKind::Equal,
+            Token::EqualEqual => TokenKind::EqualEqual,
+            Token::Greater => TokenKind::Greater,
+            Token::GreaterEqual => TokenKind::GreaterEqual,
+            Token::Less => TokenKind::Less,
+            Token::LessEqual => TokenKind::LessEqual,
+            Token::Spread => TokenKind::Spread,
+            Token::Ident(_) => TokenKind::Ident,
+            Token::Annotation(_) => TokenKind::Annotation,
+            Token::String(_) => TokenKind::String,
+            Token::Number { .. } => TokenKind::Number,
+            Token::And => TokenKind::And,
+            Token::Break => TokenKind::Break,
+            Token::Catch => TokenKind::Catch,
+            Token::Class => TokenKind::Class,
+            Token::Const => TokenKind::Const,
+            Token::Continue => TokenKind::Continue,
+            Token::Defer => TokenKind::Defer,
+            Token::Else => TokenKind::Else,
+            Token::Enum => TokenKind::Enum,
+            Token::False => TokenKind::False,
+            Token::Finally => TokenKind::Finally,
+            Token::Fun => TokenKind::Fun,
+            Token::For => TokenKind::For,
+            Token::If => TokenKind::If,
+            Token::In => TokenKind::In,
+            Token::Is => TokenKind::Is,
+            Token::Nil => TokenKind::Nil,
+            Token::Or => TokenKind::Or,
+            Token::Print => TokenKind::Print,
+            Token::Repeat => TokenKind::Repeat,
+            Token::Super => TokenKind::Super,
+            Token::Return => TokenKind::Return,
+            Token::This => TokenKind::This,
+            Token::Throw => TokenKind::Throw,
+            Token::True => TokenKind::True,
+            Token::Try => TokenKind::Try,
+            Token::Var => TokenKind::Var,
+            Token::While => TokenKind::While,
+            Token::With => TokenKind::With,
+            Token::Eof => TokenKind::Eof,
+            Token::Unknown => TokenKind::Unknown,
+            Token::Unexpected { .. } => TokenKind::Unexpected,
+            Token::InvalidNumber => TokenKind::InvalidNumber,
+            Token::LiteralTooLong => TokenKind::LiteralTooLong,
+            Token::Comment { .. } => TokenKind::Comment,
+            Token::Whitespace => TokenKind::Whitespace,
+        }
+    }
+
+    pub fn to_owned(&self) -> Token<'static> {
+        match *self {
+            Token::LeftParen => Token::LeftParen,
+            Token::RightParen => Token::RightParen,
+            Token::LeftBrace => Token::LeftBrace,
+            Token::RightBrace => Token::RightBrace,
+            Token::LeftBracket => Token::LeftBracket,
+            Token::RightBracket => Token::RightBracket,
+            Token::Comma => Token::Comma,
+            Token::Dot => Token::Dot,
+            Token::Minus => Token::Minus,
+            Token::Plus => Token::Plus,
+            Token::Semicolon => Token::Semicolon,
+            Token::Slash => Token::Slash,
+            Token::Star => Token::Star,
+            Token::Question => Token::Question,
+            Token::Colon => Token::Colon,
+            Token::Bang => Token::Bang,
+            Token::BangEqual => Token::BangEqual,
+            Token::Equal => Token::Equal,
+            Token::EqualEqual => Token::EqualEqual,
+            Token::Greater => Token::Greater,
+            Token::GreaterEqual => Token::GreaterEqual,
+            Token::Less => Token::Less,
+            Token::LessEqual => Token::LessEqual,
+            Token::Spread => Token::Spread,
+            Token::Ident(ref s) => Token::Ident(Cow::Owned(s.to_string())),
+            Token::Annotation(ref s) => Token::Annotation(Cow::Owned(s.to_string())),
+            Token::String(ref s) => Token::String(Cow::Owned(s.to_string())),
+            Token::Number { value, is_int } => Token::Number { value, is_int },
+            Token::And => Token::And,
+            Token::Break => Token::Break,
+            Token::Catch => Token::Catch,
+            Token::Class => Token::Class,
+            Token::Const => Token::Const,
+            Token::Continue => Token::Continue,
+            Token::Defer => Token::Defer,
+            Token::Else => Token::Else,
+            Token::Enum => Token::Enum,
+            Token::False => Token::False,
+            Token::Finally => Token::Finally,
+            Token::Fun => Token::Fun,
+            Token::For => Token::For,
+            Token::If => Token::If,
+            Token::In => Token::In,
+            Token::Is => Token::Is,
+            Token::Nil => Token::Nil,
+            Token::Or => Token::Or,
+            Token::Print => Token::Print,
+            Token::Repeat => Token::Repeat,
+            Token::Super => Token::Super,
+            Token::Return => Token::Return,
+            Token::This => Token::This,
+            Token::Throw => Token::Throw,
+            Token::True => Token::True,
+            Token::Try => Token::Try,
+            Token::Var => Token::Var,
+            Token::While => Token::While,
+            Token::With => Token::With,
+            Token::Eof => Token::Eof,
+            Token::Unknown => Token::Unknown,
+            Token::Unexpected { line, col } => Token::Unexpected { line, col },
+            Token::InvalidNumber => Token::InvalidNumber,
+            Token::LiteralTooLong => Token::LiteralTooLong,
+            Token::Comment { ref text, kind } => Token::Comment {
+                text: Cow::Owned(text.to_string()),
+                kind,
+            },
+            Token::Whitespace => Token::Whitespace,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error_code().is_some()
+    }
+
+    /// Stable code of an error token, `None` for every other token
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            Token::Unexpected { .. } => Some("E0001"),
+            Token::Unknown => Some("E0002"),
+            Token::InvalidNumber => Some("E0003"),
+            Token::LiteralTooLong => Some("E0004"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    /// Length in characters
+    pub len: usize,
+    /// Byte offset of the first byte
+    pub start: usize,
+    /// Byte offset one past the last byte
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Recovery {
+    /// Resume lexing right after the offending character
+    #[default]
+    SingleChar,
+    /// Skip the rest of the line after an error
+    Newline,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub recovery: Recovery,
+    /// Reject decimal literals with leading zeros, like `0123`
+    pub strict_numbers: bool,
+    /// Accept `&&` and `||` as aliases for `and` and `or`
+    pub symbolic_logic: bool,
+    /// Longest string literal accepted, in characters, excluding quotes
+    pub max_string_len: Option<usize>,
+    /// Longest number literal accepted, in characters
+    pub max_number_len: Option<usize>,
+}
+
+static KEYWORDS: [(&str, Token<'static>); 29] = [
+    ("and", Token::And),
+    ("break", Token::Break),
+    ("catch", Token::Catch),
+    ("class", Token::Class),
+    ("const", Token::Const),
+    ("continue", Token::Continue),
+    ("defer", Token::Defer),
+    ("else", Token::Else),
+    ("enum", Token::Enum),
+    ("false", Token::False),
+    ("finally", Token::Finally),
+    ("for", Token::For),
+    ("fun", Token::Fun),
+    ("if", Token::If),
+    ("in", Token::In),
+    ("is", Token::Is),
+    ("nil", Token::Nil),
+    ("or", Token::Or),
+    ("print", Token::Print),
+    ("repeat", Token::Repeat),
+    ("return", Token::Return),
+    ("super", Token::Super),
+    ("this", Token::This),
+    ("throw", Token::Throw),
+    ("true", Token::True),
+    ("try", Token::Try),
+    ("var", Token::Var),
+    ("while", Token::While),
+    ("with", Token::With),
+];
+
+const KEYWORD_SLOTS: usize = 128;
+
+/// Hash of a keyword candidate from its length and first and last bytes
+const fn keyword_hash(word: &[u8], seed: usize) -> usize {
+    let first = word[0] as usize;
+    let last = word[word.len() - 1] as usize;
+    (first * seed + last * 3 + word.len()) % KEYWORD_SLOTS
+}
+
+/// Searches, at compile time, a seed that gives every keyword its own slot
+const fn keyword_seed() -> usize {
+    let mut seed = 1;
+    'seeds: while seed < 1024 {
+        let mut taken = [false; KEYWORD_SLOTS];
+        let mut i = 0;
+        while i < KEYWORDS.len() {
+            let slot = keyword_hash(KEYWORDS[i].0.as_bytes(), seed);
+            if taken[slot] {
+                seed += 1;
+                continue 'seeds;
+            }
+            taken[slot] = true;
+            i += 1;
+        }
+        return seed;
+    }
+    panic!("no collision-free keyword seed, grow KEYWORD_SLOTS");
+}
+
+const KEYWORD_SEED: usize = keyword_seed();
+
+static KEYWORD_TABLE: [Option<usize>; KEYWORD_SLOTS] = {
+    let mut table = [None; KEYWORD_SLOTS];
+    let mut i = 0;
+    while i < KEYWORDS.len() {
+        table[keyword_hash(KEYWORDS[i].0.as_bytes(), KEYWORD_SEED)] = Some(i);
+        i += 1;
+    }
+    table
+};
+
+fn keyword(ident: &str) -> Option<Token<'static>> {
+    let (word, token) = &KEYWORDS[KEYWORD_TABLE[keyword_hash(ident.as_bytes(), KEYWORD_SEED)]?];
+    (*word == ident).then(|| token.clone())
+}
+
 const EOF_CHAR: char = '\0';
+const BOM_CHAR: char = '\u{FEFF}';
 
 struct Cursor<'a> {
+    input: &'a str,
     iter: Peekable<Chars<'a>>,
     line: usize,
     col: usize,
     prev: char,
+    consumed: usize,
+    offset: usize,
+    options: Options,
 }
 
 impl<'a> Cursor<'a> {
-    pub fn new(input: &'a str) -> Self {
-        let iter = input.chars().peekable();
+    pub fn new(input: &'a str, options: Options) -> Self {
+        let offset = if input.starts_with(BOM_CHAR) {
+            BOM_CHAR.len_utf8()
+        } else {
+            0
+        };
+        let iter = input[offset..].chars().peekable();
         Self {
+            input,
             iter,
             line: 1,
             col: 0,
             prev: EOF_CHAR,
+            consumed: 0,
+            offset,
+            options,
+        }
+    }
+
+    /// Cursor at the start of the token at `span`, as if `input` had been consumed up to it
+    fn resume(input: &'a str, options: Options, span: &Span) -> Self {
+        Self {
+            input,
+            iter: input[span.start..].chars().peekable(),
+            line: span.line,
+            col: span.col - 1,
+            prev: EOF_CHAR,
+            consumed: 0,
+            offset: span.start,
+            options,
         }
     }
 
     fn next(&mut self) -> Option<char> {
+        let next = self.iter.next()?;
+
         if self.prev == '\n' {
             self.line += 1;
             self.col = 0;
         }
         self.col += 1;
 
-        let _next = self.iter.next();
-        self.prev = _next.unwrap_or(EOF_CHAR);
+        self.prev = next;
+        self.consumed += 1;
+        self.offset += next.len_utf8();
+
+        Some(next)
+    }
 
-        _next
+    /// Line and column of the next character to be consumed
+    fn position(&self) -> (usize, usize) {
+        if self.prev == '\n' {
+            (self.line + 1, 1)
+        } else {
+            (self.line, self.col + 1)
+        }
     }
 
     fn peek(&mut self) -> Option<&char> {
         self.iter.peek()
     }
 
+    fn peek_second(&self) -> Option<char> {
+        let mut iter = self.iter.clone();
+        iter.next();
+        iter.next()
+    }
+
     fn next_matches(&mut self, expected: char) -> bool {
         match self.peek() {
             Some(actual) if *actual == expected => {
@@ -104,7 +547,8 @@ impl<'a> Cursor<'a> {
             _ => false,
         }
     }
-    fn advance_token(&mut self) -> Token {
+
+    fn advance_token(&mut self) -> Token<'a> {
         if let Some(first_char) = self.next() {
             match first_char {
                 c if c.is_whitespace() => {
@@ -115,12 +559,24 @@ impl<'a> Cursor<'a> {
                 ')' => Token::RightParen,
                 '{' => Token::LeftBrace,
                 '}' => Token::RightBrace,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
                 ',' => Token::Comma,
-                '.' => Token::Dot,
+                '.' => {
+                    if self.peek() == Some(&'.') && self.peek_second() == Some('.') {
+                        self.next();
+                        self.next();
+                        Token::Spread
+                    } else {
+                        Token::Dot
+                    }
+                }
                 '-' => Token::Minus,
                 '+' => Token::Plus,
                 ';' => Token::Semicolon,
                 '*' => Token::Star,
+                '?' => Token::Question,
+                ':' => Token::Colon,
                 '!' => {
                     if self.next_matches('=') {
                         Token::BangEqual
@@ -152,13 +608,21 @@ impl<'a> Cursor<'a> {
                 '/' => {
                     if self.next_matches('/') {
                         let comment = self.take_while(|c| c != '\n');
-                        Token::Comment(comment)
+                        Token::Comment {
+                            text: Cow::Borrowed(comment),
+                            kind: CommentKind::Line,
+                        }
+                    } else if self.next_matches('*') {
+                        self.block_comment()
                     } else {
                         Token::Slash
                     }
                 }
+                '&' if self.options.symbolic_logic && self.next_matches('&') => Token::And,
+                '|' if self.options.symbolic_logic && self.next_matches('|') => Token::Or,
                 '"' => self.string(),
-                c if c.is_digit(10) => self.number(c),
+                '@' => self.annotation(),
+                c if c.is_ascii_digit() => self.number(c),
                 c if Self::is_alpha(c) => self.identifier(c),
                 _ => Token::Unknown,
             }
@@ -167,63 +631,82 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    fn recover(&mut self) {
+        match self.options.recovery {
+            Recovery::SingleChar => {}
+            Recovery::Newline => self.eat_while(|c| c != '\n'),
+        }
+    }
+
     fn is_alpha(c: char) -> bool {
         c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(c: char) -> bool {
-        Self::is_alpha(c) || c.is_digit(10)
-    }
-
-    fn identifier(&mut self, first_char: char) -> Token {
-        let ident = format!("{}{}", first_char, self.take_while(Self::is_alphanumeric));
-
-        match ident.as_str() {
-            "and" => Token::And,
-            "class" => Token::Class,
-            "else" => Token::Else,
-            "false" => Token::False,
-            "for" => Token::For,
-            "fun" => Token::Fun,
-            "if" => Token::If,
-            "nil" => Token::Nil,
-            "or" => Token::Or,
-            "print" => Token::Print,
-            "return" => Token::Return,
-            "super" => Token::Super,
-            "this" => Token::This,
-            "true" => Token::True,
-            "var" => Token::Var,
-            "while" => Token::While,
-            _ => Token::Ident(ident),
-        }
-    }
-
-    fn number(&mut self, first_char: char) -> Token {
+        Self::is_alpha(c) || c.is_ascii_digit()
+    }
+
+    fn identifier(&mut self, first_char: char) -> Token<'a> {
+        let start = self.offset - first_char.len_utf8();
+        self.eat_while(Self::is_alphanumeric);
+        let ident = &self.input[start..self.offset];
+
+        match keyword(ident) {
+            Some(token) => token,
+            None => Token::Ident(Cow::Borrowed(ident)),
+        }
+    }
+
+    fn annotation(&mut self) -> Token<'a> {
+        match self.peek() {
+            Some(c) if Self::is_alpha(*c) => {
+                let name = self.take_while(Self::is_alphanumeric);
+                Token::Annotation(Cow::Borrowed(name))
+            }
+            _ => {
+                let (line, col) = self.position();
+                Token::Unexpected { line, col }
+            }
+        }
+    }
+
+    fn number(&mut self, first_char: char) -> Token<'a> {
+        let start = self.offset - first_char.len_utf8();
         let mut has_dot = false;
-        let number = format!(
-            "{}{}",
-            first_char,
-            self.take_while(move |c| {
-                if c.is_digit(10) {
-                    return true;
-                }
+        self.eat_while(move |c| {
+            if c.is_ascii_digit() {
+                return true;
+            }
 
-                if c == '.' && !has_dot {
-                    has_dot = true;
-                    return true;
-                }
-                false
-            })
-        );
-        if let Ok(number) = number.parse::<f64>() {
-            return Token::Number(number);
+            if c == '.' && !has_dot {
+                has_dot = true;
+                return true;
+            }
+            false
+        });
+        let number = &self.input[start..self.offset];
+        if Self::exceeds(number, self.options.max_number_len) {
+            return Token::LiteralTooLong;
+        }
+
+        if self.options.strict_numbers
+            && first_char == '0'
+            && number[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Token::InvalidNumber;
+        }
+
+        if let Ok(value) = number.parse::<f64>() {
+            return Token::Number {
+                value,
+                is_int: !number.contains('.'),
+            };
         }
 
         Token::Unknown
     }
 
-    fn string(&mut self) -> Token {
+    fn string(&mut self) -> Token<'a> {
         let mut escaped = false;
         let string = self.take_while(move |c| {
             let cont = escaped || c != '"';
@@ -232,27 +715,50 @@ impl<'a> Cursor<'a> {
         });
 
         if self.peek() != Some(&'"') {
-            return Token::Unexpected {
-                line: self.line,
-                col: self.col + 1,
-            };
+            let (line, col) = self.position();
+            return Token::Unexpected { line, col };
         }
 
         self.next();
-        Token::String(string)
+        if Self::exceeds(string, self.options.max_string_len) {
+            return Token::LiteralTooLong;
+        }
+
+        Token::String(Cow::Borrowed(string))
     }
 
-    fn take_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> String {
-        let mut string = String::new();
-        while let Some(second_char) = self.peek() {
-            if !predicate(*second_char) {
-                break;
+    fn exceeds(literal: &str, max_len: Option<usize>) -> bool {
+        max_len.is_some_and(|max_len| literal.chars().count() > max_len)
+    }
+
+    fn block_comment(&mut self) -> Token<'a> {
+        let start = self.offset;
+        loop {
+            match self.peek().copied() {
+                Some('*') if self.peek_second() == Some('/') => break,
+                Some(_) => {
+                    self.next();
+                }
+                None => {
+                    let (line, col) = self.position();
+                    return Token::Unexpected { line, col };
+                }
             }
-            string.push(*second_char);
-            self.next();
         }
 
-        string
+        let comment = &self.input[start..self.offset];
+        self.next();
+        self.next();
+        Token::Comment {
+            text: Cow::Borrowed(comment),
+            kind: CommentKind::Block,
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl FnMut(char) -> bool) -> &'a str {
+        let start = self.offset;
+        self.eat_while(predicate);
+        &self.input[start..self.offset]
     }
 
     fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
@@ -265,25 +771,174 @@ impl<'a> Cursor<'a> {
     }
 }
 
-pub fn tokenize(input: &str) -> impl Iterator<Item = Token> {
-    let mut cursor = Cursor::new(input);
-    std::iter::from_fn(move || {
-        let token = cursor.advance_token();
+pub struct Lexer<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, Options::default())
+    }
+
+    pub fn with_options(input: &'a str, options: Options) -> Self {
+        Self {
+            cursor: Cursor::new(input, options),
+        }
+    }
+
+    /// Line of the next character to be lexed
+    pub fn line(&self) -> usize {
+        self.cursor.position().0
+    }
+
+    /// Column of the next character to be lexed
+    pub fn col(&self) -> usize {
+        self.cursor.position().1
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Spanned<Token<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, col) = self.cursor.position();
+        let consumed = self.cursor.consumed;
+        let start = self.cursor.offset;
+
+        let token = self.cursor.advance_token();
+        let span = Span {
+            line,
+            col,
+            len: self.cursor.consumed - consumed,
+            start,
+            end: self.cursor.offset,
+        };
+
         match token {
             Token::Eof => None,
-            _ => Some(token),
+            _ if token.is_error() => {
+                self.cursor.recover();
+                Some(Spanned { node: token, span })
+            }
+            _ => Some(Spanned { node: token, span }),
         }
-    })
+    }
+}
+
+impl FusedIterator for Lexer<'_> {}
+
+pub fn tokenize(input: &str) -> Lexer<'_> {
+    Lexer::new(input)
+}
+
+/// Replacement of the bytes `start..end` of a source with `text`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextEdit<'e> {
+    pub start: usize,
+    pub end: usize,
+    pub text: &'e str,
+}
+
+impl TextEdit<'_> {
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(self.start..self.end, self.text);
+        edited
+    }
+}
+
+/// Tokens of `source`, the text after `edit`, from `old_tokens`, the tokens of the
+/// text before it. Only the region around the edit is lexed again, the tokens
+/// before it are kept and the ones after it are moved to their new positions.
+pub fn relex<'a>(
+    source: &'a str,
+    old_tokens: &[Spanned<Token<'a>>],
+    edit: &TextEdit,
+) -> Vec<Spanned<Token<'a>>> {
+    // A token ending at the edit can grow into it, and the one before may have
+    // peeked into it, so lexing restarts at the latter
+    let touched = old_tokens
+        .iter()
+        .position(|token| token.span.end >= edit.start)
+        .unwrap_or(old_tokens.len());
+    let restart = touched.saturating_sub(1);
+
+    let mut tokens = old_tokens[..restart].to_vec();
+    let lexer = match old_tokens.get(restart) {
+        Some(token) if restart > 0 => Lexer {
+            cursor: Cursor::resume(source, Options::default(), &token.span),
+        },
+        _ => Lexer::new(source),
+    };
+
+    // Old tokens past the edit, with their start in the new source
+    let moved = edit.start + edit.text.len();
+    let mut old = old_tokens[touched..]
+        .iter()
+        .filter(|token| token.span.start >= edit.end)
+        .map(|token| (token.span.start - edit.end + moved, token))
+        .peekable();
+
+    for token in lexer {
+        while old
+            .next_if(|(start, _)| *start < token.span.start)
+            .is_some()
+        {}
+
+        let Some((_, anchor)) = old.next_if(|(start, _)| *start == token.span.start) else {
+            tokens.push(token);
+            continue;
+        };
+
+        // From here on the source is unchanged, so the rest of the old tokens
+        // only move by as much as this one did
+        let new = token.span;
+        let anchor = anchor.span;
+        let move_position = |line: usize, col: usize| {
+            let col = if line == anchor.line {
+                col - anchor.col + new.col
+            } else {
+                col
+            };
+            (line - anchor.line + new.line, col)
+        };
+
+        tokens.push(token);
+        tokens.extend(old.map(|(start, token)| {
+            let (line, col) = move_position(token.span.line, token.span.col);
+            let node = match token.node {
+                Token::Unexpected { line, col } => {
+                    let (line, col) = move_position(line, col);
+                    Token::Unexpected { line, col }
+                }
+                ref node => node.clone(),
+            };
+            Spanned {
+                node,
+                span: Span {
+                    line,
+                    col,
+                    start,
+                    end: start + token.span.end - token.span.start,
+                    ..token.span
+                },
+            }
+        }));
+        break;
+    }
+
+    tokens
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn assert_tokens(
-        mut actual: impl Iterator<Item = Token>,
-        expected: impl IntoIterator<Item = Token>,
+    fn assert_tokens<'a>(
+        actual: impl Iterator<Item = Spanned<Token<'a>>>,
+        expected: impl IntoIterator<Item = Token<'a>>,
     ) {
+        let mut actual = actual.map(|token| token.node);
         for (i, expected_item) in expected.into_iter().enumerate() {
             assert_eq!(actual.next(), Some(expected_item), "comparing item {}", i);
         }
@@ -292,7 +947,7 @@ mod tests {
 
     #[test]
     fn single_character_tokens() {
-        let source = r#"({}),.-+;/*"#;
+        let source = r#"({[]}),.-+;*/?:"#;
         let actual = tokenize(source);
 
         assert_tokens(
@@ -300,6 +955,8 @@ mod tests {
             vec![
                 Token::LeftParen,
                 Token::LeftBrace,
+                Token::LeftBracket,
+                Token::RightBracket,
                 Token::RightBrace,
                 Token::RightParen,
                 Token::Comma,
@@ -307,8 +964,78 @@ mod tests {
                 Token::Minus,
                 Token::Plus,
                 Token::Semicolon,
-                Token::Slash,
                 Token::Star,
+                Token::Slash,
+                Token::Question,
+                Token::Colon,
+            ],
+        );
+    }
+
+    #[test]
+    fn mismatched_brackets() {
+        let source = "foo(bar[baz)";
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Ident(Cow::Borrowed("foo")),
+                Token::LeftParen,
+                Token::Ident(Cow::Borrowed("bar")),
+                Token::LeftBracket,
+                Token::Ident(Cow::Borrowed("baz")),
+                Token::RightParen,
+            ],
+        );
+    }
+
+    #[test]
+    fn named_arguments() {
+        let source = "f(y:2,x:1)";
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Ident(Cow::Borrowed("f")),
+                Token::LeftParen,
+                Token::Ident(Cow::Borrowed("y")),
+                Token::Colon,
+                Token::Number {
+                    value: 2f64,
+                    is_int: true,
+                },
+                Token::Comma,
+                Token::Ident(Cow::Borrowed("x")),
+                Token::Colon,
+                Token::Number {
+                    value: 1f64,
+                    is_int: true,
+                },
+                Token::RightParen,
+            ],
+        );
+    }
+
+    #[test]
+    fn type_annotations() {
+        let source = "fun f(x:Number):Number";
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Fun,
+                Token::Whitespace,
+                Token::Ident(Cow::Borrowed("f")),
+                Token::LeftParen,
+                Token::Ident(Cow::Borrowed("x")),
+                Token::Colon,
+                Token::Ident(Cow::Borrowed("Number")),
+                Token::RightParen,
+                Token::Colon,
+                Token::Ident(Cow::Borrowed("Number")),
             ],
         );
     }
@@ -347,6 +1074,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn spread() {
+        let source = r#"f(...args) .. ."#;
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Ident("f".into()),
+                Token::LeftParen,
+                Token::Spread,
+                Token::Ident("args".into()),
+                Token::RightParen,
+                Token::Whitespace,
+                Token::Dot,
+                Token::Dot,
+                Token::Whitespace,
+                Token::Dot,
+            ],
+        );
+    }
+
     #[test]
     fn idents() {
         let source = r#"variable1 variable_2 cammelCaseVariable _undescore_first"#;
@@ -355,13 +1104,13 @@ mod tests {
         assert_tokens(
             actual,
             vec![
-                Token::Ident("variable1".to_string()),
+                Token::Ident("variable1".into()),
                 Token::Whitespace,
-                Token::Ident("variable_2".to_string()),
+                Token::Ident("variable_2".into()),
                 Token::Whitespace,
-                Token::Ident("cammelCaseVariable".to_string()),
+                Token::Ident("cammelCaseVariable".into()),
                 Token::Whitespace,
-                Token::Ident("_undescore_first".to_string()),
+                Token::Ident("_undescore_first".into()),
             ],
         );
     }
@@ -376,33 +1125,53 @@ mod tests {
         assert_tokens(
             actual,
             vec![
-                Token::String("Valid string even if keywords in".to_string()),
+                Token::String("Valid string even if keywords in".into()),
                 Token::Whitespace,
-                Token::String("Escaped \\\"string\\\"".to_string()),
+                Token::String("Escaped \\\"string\\\"".into()),
                 Token::Whitespace,
                 Token::Unexpected { line: 3, col: 31 },
             ],
         );
+
+        let mut lexer = tokenize("\"abc\n");
+        assert_eq!(
+            lexer.next().map(|token| token.node),
+            Some(Token::Unexpected { line: 2, col: 1 })
+        );
+        assert_eq!((lexer.line(), lexer.col()), (2, 1));
     }
 
     #[test]
     fn keywords() {
         let source = r#"and
+break
+catch
 class
+const
+continue
+defer
 else
+enum
 false
+finally
 fun
 for
 if
+in
+is
 nil
 or
 print
+repeat
 super
 return
 this
+throw
 true
+try
 var
-while"#;
+while
+with"#;
         let actual = tokenize(source);
 
         assert_tokens(
@@ -410,39 +1179,101 @@ while"#;
             vec![
                 Token::And,
                 Token::Whitespace,
+                Token::Break,
+                Token::Whitespace,
+                Token::Catch,
+                Token::Whitespace,
                 Token::Class,
                 Token::Whitespace,
+                Token::Const,
+                Token::Whitespace,
+                Token::Continue,
+                Token::Whitespace,
+                Token::Defer,
+                Token::Whitespace,
                 Token::Else,
                 Token::Whitespace,
+                Token::Enum,
+                Token::Whitespace,
                 Token::False,
                 Token::Whitespace,
+                Token::Finally,
+                Token::Whitespace,
                 Token::Fun,
                 Token::Whitespace,
                 Token::For,
                 Token::Whitespace,
                 Token::If,
                 Token::Whitespace,
+                Token::In,
+                Token::Whitespace,
+                Token::Is,
+                Token::Whitespace,
                 Token::Nil,
                 Token::Whitespace,
                 Token::Or,
                 Token::Whitespace,
                 Token::Print,
                 Token::Whitespace,
+                Token::Repeat,
+                Token::Whitespace,
                 Token::Super,
                 Token::Whitespace,
                 Token::Return,
                 Token::Whitespace,
                 Token::This,
                 Token::Whitespace,
+                Token::Throw,
+                Token::Whitespace,
                 Token::True,
                 Token::Whitespace,
+                Token::Try,
+                Token::Whitespace,
                 Token::Var,
                 Token::Whitespace,
                 Token::While,
+                Token::Whitespace,
+                Token::With,
             ],
         );
     }
 
+    #[test]
+    fn for_in() {
+        let source = r#"for(c in "héllo")"#;
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::For,
+                Token::LeftParen,
+                Token::Ident(Cow::Borrowed("c")),
+                Token::Whitespace,
+                Token::In,
+                Token::Whitespace,
+                Token::String(Cow::Borrowed("héllo")),
+                Token::RightParen,
+            ],
+        );
+        assert_tokens(
+            tokenize("index"),
+            vec![Token::Ident(Cow::Borrowed("index"))],
+        );
+    }
+
+    #[test]
+    fn keyword_table() {
+        for (word, token) in &KEYWORDS {
+            assert_eq!(keyword(word).as_ref(), Some(token), "looking up {}", word);
+            assert_tokens(tokenize(word), vec![token.clone()]);
+        }
+
+        for word in ["a", "an", "andy", "classy", "iff", "x", "_", "whilst"] {
+            assert_eq!(keyword(word), None, "looking up {}", word);
+        }
+    }
+
     #[test]
     fn comments() {
         let source = r#"// comment! no var/if keyword"#;
@@ -450,7 +1281,637 @@ while"#;
 
         assert_tokens(
             actual,
-            vec![Token::Comment(" comment! no var/if keyword".to_string())],
+            vec![Token::Comment {
+                text: " comment! no var/if keyword".into(),
+                kind: CommentKind::Line,
+            }],
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        let source = "/* spans\ntwo lines */ 1 /* unterminated";
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Comment {
+                    text: " spans\ntwo lines ".into(),
+                    kind: CommentKind::Block,
+                },
+                Token::Whitespace,
+                Token::Number {
+                    value: 1f64,
+                    is_int: true,
+                },
+                Token::Whitespace,
+                Token::Unexpected { line: 2, col: 31 },
+            ],
+        );
+
+        assert_tokens(
+            tokenize("/* x\n"),
+            vec![Token::Unexpected { line: 2, col: 1 }],
+        );
+    }
+
+    #[test]
+    fn doc_comment_position() {
+        let source = "var a;\n/** Adds two numbers */\nfun add(a, b) {}";
+        let mut tokens = tokenize(source).filter(|token| token.node != Token::Whitespace);
+
+        let comment = tokens
+            .find(|token| matches!(token.node, Token::Comment { .. }))
+            .unwrap();
+        assert_eq!(
+            comment.node,
+            Token::Comment {
+                text: "* Adds two numbers ".into(),
+                kind: CommentKind::Block,
+            }
+        );
+        assert_eq!(
+            comment.span,
+            Span {
+                line: 2,
+                col: 1,
+                len: 23,
+                start: 7,
+                end: 30,
+            }
+        );
+
+        let documented = tokens.next().unwrap();
+        assert_eq!(documented.node, Token::Fun);
+        assert_eq!((documented.span.line, documented.span.col), (3, 1));
+    }
+
+    #[test]
+    fn annotations() {
+        let source = r#"@test fun foo() {}
+@ fun"#;
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Annotation("test".into()),
+                Token::Whitespace,
+                Token::Fun,
+                Token::Whitespace,
+                Token::Ident("foo".into()),
+                Token::LeftParen,
+                Token::RightParen,
+                Token::Whitespace,
+                Token::LeftBrace,
+                Token::RightBrace,
+                Token::Whitespace,
+                Token::Unexpected { line: 2, col: 2 },
+                Token::Whitespace,
+                Token::Fun,
+            ],
+        );
+    }
+
+    #[test]
+    fn leading_bom_is_stripped() {
+        let source = "\u{FEFF}var \"abc";
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Var,
+                Token::Whitespace,
+                Token::Unexpected { line: 1, col: 9 },
+            ],
+        );
+    }
+
+    #[test]
+    fn inner_bom_is_unknown() {
+        let source = "var\u{FEFF}";
+        let actual = tokenize(source);
+
+        assert_tokens(actual, vec![Token::Var, Token::Unknown]);
+    }
+
+    #[test]
+    fn token_kinds() {
+        use std::collections::BTreeMap;
+
+        let source = r#"var a = 1; var b = "two";"#;
+        let mut counts = BTreeMap::new();
+        for token in tokenize(source) {
+            *counts.entry(token.node.kind()).or_insert(0) += 1;
+        }
+
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            vec![
+                (TokenKind::Semicolon, 2),
+                (TokenKind::Equal, 2),
+                (TokenKind::Ident, 2),
+                (TokenKind::String, 1),
+                (TokenKind::Number, 1),
+                (TokenKind::Var, 2),
+                (TokenKind::Whitespace, 7),
+            ],
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        let source = r#"5 5.0 12.5"#;
+        let actual = tokenize(source);
+
+        assert_tokens(
+            actual,
+            vec![
+                Token::Number {
+                    value: 5f64,
+                    is_int: true,
+                },
+                Token::Whitespace,
+                Token::Number {
+                    value: 5f64,
+                    is_int: false,
+                },
+                Token::Whitespace,
+                Token::Number {
+                    value: 12.5,
+                    is_int: false,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn literal_length_limits() {
+        let source = r#""short" "too long" 1234 12345 1.234"#;
+        let limited = Options {
+            max_string_len: Some(5),
+            max_number_len: Some(4),
+            ..Default::default()
+        };
+        let spans: Vec<_> = Lexer::with_options(source, limited)
+            .filter(|token| token.node != Token::Whitespace)
+            .map(|token| (token.node, token.span.col))
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (Token::String("short".into()), 1),
+                (Token::LiteralTooLong, 9),
+                (
+                    Token::Number {
+                        value: 1234f64,
+                        is_int: true,
+                    },
+                    20
+                ),
+                (Token::LiteralTooLong, 25),
+                (Token::LiteralTooLong, 31),
+            ]
+        );
+
+        assert_eq!(
+            tokenize(source)
+                .filter(|token| token.node.is_error())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        let source = "\"abc";
+        let codes = |source, options| {
+            Lexer::with_options(source, options)
+                .filter_map(|token| token.node.error_code())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(codes(source, Options::default()), ["E0001"]);
+        assert_eq!(
+            codes("# @ /* x", Options::default()),
+            ["E0002", "E0001", "E0001"]
+        );
+        assert_eq!(
+            codes(
+                "012 \"abcd\"",
+                Options {
+                    strict_numbers: true,
+                    max_string_len: Some(3),
+                    ..Default::default()
+                }
+            ),
+            ["E0003", "E0004"]
+        );
+        assert_eq!(Token::Semicolon.error_code(), None);
+    }
+
+    #[test]
+    fn error_recovery() {
+        let source = "var # $ % ^\nvar";
+        let count_errors = |recovery| {
+            Lexer::with_options(
+                source,
+                Options {
+                    recovery,
+                    ..Default::default()
+                },
+            )
+            .filter(|token| token.node.is_error())
+            .count()
+        };
+
+        assert_eq!(count_errors(Recovery::SingleChar), 4);
+        assert_eq!(count_errors(Recovery::Newline), 1);
+
+        assert_tokens(
+            Lexer::with_options(
+                source,
+                Options {
+                    recovery: Recovery::Newline,
+                    ..Default::default()
+                },
+            ),
+            vec![
+                Token::Var,
+                Token::Whitespace,
+                Token::Unknown,
+                Token::Whitespace,
+                Token::Var,
+            ],
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let source = "var name = \"lox\";\n  print name;";
+        let spans: Vec<_> = tokenize(source)
+            .filter(|token| token.node != Token::Whitespace)
+            .map(|token| (token.node, token.span))
+            .collect();
+
+        let span = |line, col, len, start| Span {
+            line,
+            col,
+            len,
+            start,
+            end: start + len,
+        };
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Var, span(1, 1, 3, 0)),
+                (Token::Ident("name".into()), span(1, 5, 4, 4)),
+                (Token::Equal, span(1, 10, 1, 9)),
+                (Token::String("lox".into()), span(1, 12, 5, 11)),
+                (Token::Semicolon, span(1, 17, 1, 16)),
+                (Token::Print, span(2, 3, 5, 20)),
+                (Token::Ident("name".into()), span(2, 9, 4, 26)),
+                (Token::Semicolon, span(2, 13, 1, 30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn byte_offsets() {
+        let source = "\u{FEFF}var s = \"héllo\"; // ü\nprint s;";
+        let tokens: Vec<_> = tokenize(source)
+            .filter(|token| token.node != Token::Whitespace)
+            .collect();
+
+        let offsets: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.span.len, token.span.start, token.span.end))
+            .collect();
+        assert_eq!(
+            offsets,
+            vec![
+                (3, 3, 6),
+                (1, 7, 8),
+                (1, 9, 10),
+                (7, 11, 19),
+                (1, 19, 20),
+                (4, 21, 26),
+                (5, 27, 32),
+                (1, 33, 34),
+                (1, 34, 35),
+            ]
+        );
+
+        for token in &tokens {
+            let lexeme = &source[token.span.start..token.span.end];
+            assert_eq!(lexeme.chars().count(), token.span.len, "{:?}", token);
+        }
+        assert_eq!(
+            &source[tokens[3].span.start..tokens[3].span.end],
+            "\"héllo\""
+        );
+        assert_eq!(&source[tokens[5].span.start..tokens[5].span.end], "// ü");
+    }
+
+    #[test]
+    fn lexer_iterator() {
+        let source = "var a;\nvar b;";
+        let mut lexer = Lexer::new(source);
+        assert_eq!(lexer.line(), 1);
+
+        let vars: Vec<_> = lexer
+            .by_ref()
+            .take_while(|token| token.node != Token::Semicolon)
+            .filter(|token| matches!(token.node, Token::Ident(_)))
+            .map(|token| token.node)
+            .collect();
+        assert_eq!(vars, vec![Token::Ident("a".into())]);
+        assert_eq!((lexer.line(), lexer.col()), (1, 7));
+
+        assert_eq!(lexer.by_ref().count(), 5);
+        assert_eq!((lexer.line(), lexer.col()), (2, 7));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.next(), None);
+        assert_eq!((lexer.line(), lexer.col()), (2, 7));
+    }
+
+    #[test]
+    fn end_position() {
+        for (source, end) in [("ab", (1, 3)), ("ab\n", (2, 1)), ("", (1, 1))] {
+            let mut lexer = Lexer::new(source);
+            lexer.by_ref().for_each(drop);
+            assert_eq!((lexer.line(), lexer.col()), end, "lexing {:?}", source);
+
+            assert_eq!(lexer.next(), None);
+            assert_eq!(lexer.next(), None);
+            assert_eq!((lexer.line(), lexer.col()), end, "lexing {:?}", source);
+        }
+    }
+
+    /// Applies `edits` one after the other, checking each relex against a full one
+    fn assert_relex(source: &str, edits: &[TextEdit]) {
+        let owned = |token: Spanned<Token>| Spanned {
+            node: token.node.to_owned(),
+            span: token.span,
+        };
+
+        let mut source = source.to_string();
+        let mut tokens: Vec<_> = tokenize(&source).map(owned).collect();
+        for edit in edits {
+            let edited = edit.apply(&source);
+            let relexed = relex(&edited, &tokens, edit);
+            let expected: Vec<_> = tokenize(&edited).collect();
+            assert_eq!(relexed, expected, "relexing {:?} after {:?}", edited, edit);
+
+            tokens = relexed.into_iter().map(owned).collect();
+            source = edited;
+        }
+    }
+
+    #[test]
+    fn relex_matches_full_lex() {
+        let source = "var abc = 1;\nprint abc == 2;\n// done";
+        assert_relex(
+            source,
+            &[
+                // Inside an identifier
+                TextEdit {
+                    start: 5,
+                    end: 5,
+                    text: "x",
+                },
+                // Merging `=` `=` into `==`
+                TextEdit {
+                    start: 10,
+                    end: 10,
+                    text: "=",
+                },
+                // New lines before later tokens
+                TextEdit {
+                    start: 0,
+                    end: 0,
+                    text: "\n\n",
+                },
+                // Replacing across lines
+                TextEdit {
+                    start: 8,
+                    end: 20,
+                    text: "b;\nvar c",
+                },
+                // Appending at the end
+                TextEdit {
+                    start: 34,
+                    end: 34,
+                    text: "\nnil",
+                },
+                // Deleting everything
+                TextEdit {
+                    start: 0,
+                    end: 38,
+                    text: "",
+                },
+                // Into an empty source
+                TextEdit {
+                    start: 0,
+                    end: 0,
+                    text: "fun f() {}",
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn relex_every_edit() {
+        let source = "a.. =1;\n\"s\" /*c*/ // d\n@x #";
+        for start in 0..=source.len() {
+            for end in start..=source.len() {
+                for text in ["", "\"", "/", "*", "\n", "=", ".", "a", "#"] {
+                    assert_relex(source, &[TextEdit { start, end, text }]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn relex_with_lookahead() {
+        assert_relex(
+            "a..b c",
+            &[
+                TextEdit {
+                    start: 3,
+                    end: 4,
+                    text: ".",
+                },
+                TextEdit {
+                    start: 1,
+                    end: 2,
+                    text: "",
+                },
+                TextEdit {
+                    start: 5,
+                    end: 5,
+                    text: "/",
+                },
+            ],
+        );
+        assert_relex(
+            "1 / 2",
+            &[TextEdit {
+                start: 3,
+                end: 3,
+                text: "/",
+            }],
+        );
+    }
+
+    #[test]
+    fn relex_strings_and_comments() {
+        let source = "var a = \"x\";\n/* note */ var b;";
+        assert_relex(
+            source,
+            &[
+                // Opening a string that runs to the end
+                TextEdit {
+                    start: 8,
+                    end: 8,
+                    text: "\"",
+                },
+                // Closing it again
+                TextEdit {
+                    start: 8,
+                    end: 9,
+                    text: "",
+                },
+                // Unclosing the block comment
+                TextEdit {
+                    start: 21,
+                    end: 23,
+                    text: "",
+                },
+                TextEdit {
+                    start: 21,
+                    end: 21,
+                    text: "*/",
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn relex_moves_error_positions() {
+        let source = "# var a;\nprint \"open";
+        assert_relex(
+            source,
+            &[
+                TextEdit {
+                    start: 0,
+                    end: 1,
+                    text: "\n\n",
+                },
+                TextEdit {
+                    start: 2,
+                    end: 2,
+                    text: "@",
+                },
+                TextEdit {
+                    start: 11,
+                    end: 11,
+                    text: "xyz",
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn strict_numbers() {
+        let source = r#"0123 0 0.5 10"#;
+        let strict = Options {
+            strict_numbers: true,
+            ..Default::default()
+        };
+
+        assert_tokens(
+            Lexer::with_options(source, strict),
+            vec![
+                Token::InvalidNumber,
+                Token::Whitespace,
+                Token::Number {
+                    value: 0f64,
+                    is_int: true,
+                },
+                Token::Whitespace,
+                Token::Number {
+                    value: 0.5,
+                    is_int: false,
+                },
+                Token::Whitespace,
+                Token::Number {
+                    value: 10f64,
+                    is_int: true,
+                },
+            ],
+        );
+
+        assert_eq!(
+            tokenize("0123").next().map(|token| token.node),
+            Some(Token::Number {
+                value: 123f64,
+                is_int: true,
+            })
+        );
+    }
+
+    #[test]
+    fn borrowed_tokens() {
+        let source = r#"var name = "lox"; // comment"#;
+        let range = source.as_bytes().as_ptr_range();
+
+        let tokens: Vec<_> = tokenize(source).map(|token| token.node).collect();
+        for token in &tokens {
+            match token {
+                Token::Ident(s) | Token::String(s) | Token::Comment { text: s, .. } => {
+                    assert!(matches!(s, Cow::Borrowed(_)), "{:?} is owned", token);
+                    assert!(range.contains(&s.as_ptr()), "{:?} is not in source", token);
+                }
+                _ => {}
+            }
+        }
+
+        let owned: Vec<Token<'static>> = tokens.iter().map(Token::to_owned).collect();
+        assert_eq!(owned, tokens);
+        assert!(matches!(&owned[2], Token::Ident(Cow::Owned(_))));
+    }
+
+    #[test]
+    fn symbolic_logic() {
+        let symbolic = || Options {
+            symbolic_logic: true,
+            ..Default::default()
+        };
+        let tokens = |source| {
+            Lexer::with_options(source, symbolic())
+                .map(|token| token.node)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tokens("a && b || c"), tokens("a and b or c"));
+        assert_tokens(
+            Lexer::with_options("& |", symbolic()),
+            vec![Token::Unknown, Token::Whitespace, Token::Unknown],
+        );
+        assert_tokens(
+            tokenize("a && b"),
+            vec![
+                Token::Ident("a".into()),
+                Token::Whitespace,
+                Token::Unknown,
+                Token::Unknown,
+                Token::Whitespace,
+                Token::Ident("b".into()),
+            ],
         );
     }
 
@@ -469,9 +1930,9 @@ print fib(8); // expect: 21"#;
             vec![
                 Token::Fun,
                 Token::Whitespace,
-                Token::Ident("fib".to_string()),
+                Token::Ident("fib".into()),
                 Token::LeftParen,
-                Token::Ident("n".to_string()),
+                Token::Ident("n".into()),
                 Token::RightParen,
                 Token::Whitespace,
                 Token::LeftBrace,
@@ -479,38 +1940,47 @@ print fib(8); // expect: 21"#;
                 Token::If,
                 Token::Whitespace,
                 Token::LeftParen,
-                Token::Ident("n".to_string()),
+                Token::Ident("n".into()),
                 Token::Whitespace,
                 Token::Less,
                 Token::Whitespace,
-                Token::Number(2f64),
+                Token::Number {
+                    value: 2f64,
+                    is_int: true,
+                },
                 Token::RightParen,
                 Token::Whitespace,
                 Token::Return,
                 Token::Whitespace,
-                Token::Ident("n".to_string()),
+                Token::Ident("n".into()),
                 Token::Semicolon,
                 Token::Whitespace,
                 Token::Return,
                 Token::Whitespace,
-                Token::Ident("fib".to_string()),
+                Token::Ident("fib".into()),
                 Token::LeftParen,
-                Token::Ident("n".to_string()),
+                Token::Ident("n".into()),
                 Token::Whitespace,
                 Token::Minus,
                 Token::Whitespace,
-                Token::Number(1f64),
+                Token::Number {
+                    value: 1f64,
+                    is_int: true,
+                },
                 Token::RightParen,
                 Token::Whitespace,
                 Token::Plus,
                 Token::Whitespace,
-                Token::Ident("fib".to_string()),
+                Token::Ident("fib".into()),
                 Token::LeftParen,
-                Token::Ident("n".to_string()),
+                Token::Ident("n".into()),
                 Token::Whitespace,
                 Token::Minus,
                 Token::Whitespace,
-                Token::Number(2f64),
+                Token::Number {
+                    value: 2f64,
+                    is_int: true,
+                },
                 Token::RightParen,
                 Token::Semicolon,
                 Token::Whitespace,
@@ -518,13 +1988,19 @@ print fib(8); // expect: 21"#;
                 Token::Whitespace,
                 Token::Print,
                 Token::Whitespace,
-                Token::Ident("fib".to_string()),
+                Token::Ident("fib".into()),
                 Token::LeftParen,
-                Token::Number(8f64),
+                Token::Number {
+                    value: 8f64,
+                    is_int: true,
+                },
                 Token::RightParen,
                 Token::Semicolon,
                 Token::Whitespace,
-                Token::Comment(" expect: 21".to_string()),
+                Token::Comment {
+                    text: " expect: 21".into(),
+                    kind: CommentKind::Line,
+                },
             ],
         );
     }
@@ -1,68 +1,383 @@
+use std::ffi::OsStr;
 use std::fs::read_to_string;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-mod lexer;
+use rlox::lexer::{self, Spanned, Token};
+
+mod color;
+mod explain;
+mod repl;
+
+use color::{Color, paint};
+use repl::Repl;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     /// Script to run
     script: Option<PathBuf>,
+
+    /// Stop reporting errors after this many
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    max_errors: usize,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Emit::Tokens)]
+    emit: Emit,
+
+    /// Never color diagnostics
+    #[arg(long)]
+    no_color: bool,
+
+    /// Explain an error code, such as E0001, and exit
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
 }
 
-fn run(source: &str) {
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Emit {
+    /// Debug dump of each token
+    Tokens,
+    /// JSON array of tokens with their positions
+    TokensJson,
+}
+
+fn run(
+    source: &str,
+    cli: &Cli,
+    color: bool,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> std::io::Result<()> {
+    let mut errors = 0;
+    let mut emitted = 0;
+    if cli.emit == Emit::TokensJson {
+        write!(out, "[")?;
+    }
+
     for token in lexer::tokenize(source) {
-        match token {
-            lexer::Token::Whitespace => {}
-            _ => {
-                println!("{:?}", token);
+        if token.node == Token::Whitespace {
+            continue;
+        }
+
+        if let Some(code) = token.node.error_code() {
+            errors += 1;
+            if errors <= cli.max_errors {
+                let location = format!("{}:{}:", token.span.line, token.span.col);
+                writeln!(
+                    err,
+                    "{} {} {:?}",
+                    paint(&location, Color::Cyan, color),
+                    paint(&format!("error[{code}]:"), Color::Red, color),
+                    token.node
+                )?;
             }
         }
+
+        match cli.emit {
+            Emit::Tokens if token.node.is_error() => {}
+            Emit::Tokens => writeln!(out, "{:?}", token.node)?,
+            Emit::TokensJson => {
+                let separator = if emitted == 0 { "\n" } else { ",\n" };
+                write!(out, "{}  {}", separator, token_json(&token))?;
+            }
+        }
+        emitted += 1;
+    }
+
+    if cli.emit == Emit::TokensJson {
+        writeln!(out, "\n]")?;
+    }
+
+    if errors > cli.max_errors {
+        writeln!(err, "... and {} more errors", errors - cli.max_errors)?;
     }
+
+    Ok(())
 }
 
-fn run_script(path: PathBuf) -> std::io::Result<()> {
+/// Error tokens are included with their kind and a null value. Numbers too large
+/// for an `f64` also get a null value, since JSON has no representation for infinity
+fn token_json(token: &Spanned<Token>) -> String {
+    let value = match &token.node {
+        Token::Ident(s)
+        | Token::Annotation(s)
+        | Token::String(s)
+        | Token::Comment { text: s, .. } => json_string(s),
+        Token::Number { value, .. } if value.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    };
+
+    format!(
+        r#"{{"kind": {}, "value": {}, "line": {}, "col": {}, "length": {}}}"#,
+        json_string(&format!("{:?}", token.node.kind())),
+        value,
+        token.span.line,
+        token.span.col,
+        token.span.len,
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Colors are used only on a terminal, unless disabled by `--no-color` or a
+/// non-empty `NO_COLOR`
+fn use_color(cli: &Cli, no_color_env: Option<&OsStr>, is_tty: bool) -> bool {
+    !cli.no_color && no_color_env.is_none_or(|value| value.is_empty()) && is_tty
+}
+
+/// Prints the explanation of `code`, returning whether it is a known error code
+fn run_explain(code: &str, out: &mut impl Write, err: &mut impl Write) -> std::io::Result<bool> {
+    let Some(text) = explain::explanation(code) else {
+        writeln!(err, "no explanation for error code '{code}'")?;
+        return Ok(false);
+    };
+
+    out.write_all(text.as_bytes())?;
+    Ok(true)
+}
+
+fn run_script(path: &PathBuf, cli: &Cli, color: bool) -> std::io::Result<()> {
     let content = read_to_string(path)?;
-    run(&content);
-    Ok(())
+    run(
+        &content,
+        cli,
+        color,
+        &mut std::io::stdout(),
+        &mut std::io::stderr(),
+    )
 }
 
-fn run_prompt() -> std::io::Result<()> {
+fn run_prompt(cli: &Cli, color: bool) -> std::io::Result<()> {
     let mut stdout = std::io::stdout();
     let stdin = std::io::stdin();
 
-    let version = env!("CARGO_PKG_VERSION");
-    let name = env!("CARGO_PKG_NAME");
-    let authors = env!("CARGO_PKG_AUTHORS");
-    let quit = "q!";
+    let mut repl = Repl::new(cli, color);
+    stdout.write_all(repl.banner().as_bytes())?;
 
-    stdout.write_all(
-        format!("Welcome to {name} version {version} by {authors}\nUse {quit} to quit\n")
-            .as_bytes(),
-    )?;
-
-    loop {
+    while let Some(prompt) = repl.prompt() {
         let mut line = String::new();
-        stdout.write_all(b"> ")?;
+        stdout.write_all(prompt.as_bytes())?;
         stdout.flush()?;
         let bytes = stdin.read_line(&mut line)?;
-        if line == format!("{quit}\n") || bytes == 0 {
-            break;
-        }
-        run(&line);
+        let output = repl.feed((bytes > 0).then_some(line.as_str()))?;
+        stdout.write_all(output.out.as_bytes())?;
+        std::io::stderr().write_all(output.err.as_bytes())?;
     }
 
     Ok(())
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    let no_color_env = std::env::var_os("NO_COLOR");
+    let color = use_color(
+        &cli,
+        no_color_env.as_deref(),
+        std::io::stderr().is_terminal(),
+    );
 
-    let _ = match cli.script {
-        Some(path) => run_script(path),
-        None => run_prompt(),
+    if let Some(code) = &cli.explain {
+        return match run_explain(code, &mut std::io::stdout(), &mut std::io::stderr()) {
+            Ok(true) => ExitCode::SUCCESS,
+            _ => ExitCode::FAILURE,
+        };
+    }
+
+    let _ = match &cli.script {
+        Some(path) => run_script(path, &cli, color),
+        None => run_prompt(&cli, color),
     };
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_to_strings(source: &str, args: &[&str]) -> (String, String) {
+        let cli = Cli::parse_from(std::iter::once("rlox").chain(args.iter().copied()));
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        run(source, &cli, false, &mut out, &mut err).unwrap();
+        (
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn max_errors_caps_reported_errors() {
+        let source = "var # # # # # # # #;";
+        let (out, err) = run_to_strings(source, &["--max-errors", "3"]);
+
+        assert_eq!(out, "Var\nSemicolon\n");
+        assert_eq!(
+            err,
+            "1:5: error[E0002]: Unknown\n1:7: error[E0002]: Unknown\n1:9: error[E0002]: Unknown\n... and 5 more errors\n"
+        );
+    }
+
+    #[test]
+    fn max_errors_no_summary_under_limit() {
+        let source = "# #";
+        let (_, err) = run_to_strings(source, &["--max-errors", "3"]);
+
+        assert_eq!(
+            err,
+            "1:1: error[E0002]: Unknown\n1:3: error[E0002]: Unknown\n"
+        );
+    }
+
+    #[test]
+    fn use_color_only_on_terminals() {
+        let cli = Cli::parse_from(["rlox"]);
+        assert!(use_color(&cli, None, true));
+        assert!(use_color(&cli, Some(OsStr::new("")), true));
+        assert!(!use_color(&cli, None, false));
+    }
+
+    #[test]
+    fn no_color_flag_wins_on_terminals() {
+        let cli = Cli::parse_from(["rlox", "--no-color"]);
+        assert!(!use_color(&cli, None, true));
+    }
+
+    #[test]
+    fn no_color_env_wins_on_terminals() {
+        let cli = Cli::parse_from(["rlox"]);
+        assert!(!use_color(&cli, Some(OsStr::new("1")), true));
+    }
+
+    #[test]
+    fn no_color_diagnostics() {
+        let cli = Cli::parse_from(["rlox", "--no-color"]);
+        let color = use_color(&cli, None, true);
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        run("#", &cli, color, &mut out, &mut err).unwrap();
+
+        let err = String::from_utf8(err).unwrap();
+        assert_eq!(err, "1:1: error[E0002]: Unknown\n");
+        assert!(!err.contains('\x1b'));
+    }
+
+    #[test]
+    fn colored_diagnostics() {
+        let cli = Cli::parse_from(["rlox"]);
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        run("#", &cli, true, &mut out, &mut err).unwrap();
+
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "\x1b[36m1:1:\x1b[0m \x1b[1;31merror[E0002]:\x1b[0m Unknown\n"
+        );
+    }
+
+    #[test]
+    fn emit_tokens_json() {
+        let source = "print \"a \\\"b\\\"\";\nprint 1.5;";
+        let (out, _) = run_to_strings(source, &["--emit", "tokens-json"]);
+
+        assert_eq!(
+            out,
+            r#"[
+  {"kind": "Print", "value": null, "line": 1, "col": 1, "length": 5},
+  {"kind": "String", "value": "a \\\"b\\\"", "line": 1, "col": 7, "length": 9},
+  {"kind": "Semicolon", "value": null, "line": 1, "col": 16, "length": 1},
+  {"kind": "Print", "value": null, "line": 2, "col": 1, "length": 5},
+  {"kind": "Number", "value": 1.5, "line": 2, "col": 7, "length": 3},
+  {"kind": "Semicolon", "value": null, "line": 2, "col": 10, "length": 1}
+]
+"#
+        );
+    }
+
+    #[test]
+    fn emit_tokens_json_overflowing_number() {
+        let source = format!("{};", "9".repeat(400));
+        let (out, _) = run_to_strings(&source, &["--emit", "tokens-json"]);
+
+        assert_eq!(
+            out,
+            r#"[
+  {"kind": "Number", "value": null, "line": 1, "col": 1, "length": 400},
+  {"kind": "Semicolon", "value": null, "line": 1, "col": 401, "length": 1}
+]
+"#
+        );
+    }
+
+    #[test]
+    fn emit_tokens_json_includes_errors() {
+        let (out, err) = run_to_strings("# nil", &["--emit", "tokens-json"]);
+
+        assert_eq!(err, "1:1: error[E0002]: Unknown\n");
+        assert_eq!(
+            out,
+            r#"[
+  {"kind": "Unknown", "value": null, "line": 1, "col": 1, "length": 1},
+  {"kind": "Nil", "value": null, "line": 1, "col": 3, "length": 3}
+]
+"#
+        );
+    }
+
+    #[test]
+    fn emit_tokens_json_empty() {
+        let (out, _) = run_to_strings("", &["--emit", "tokens-json"]);
+        assert_eq!(out, "[\n]\n");
+    }
+
+    #[test]
+    fn diagnostics_report_error_codes() {
+        let (_, err) = run_to_strings("\"abc", &[]);
+        assert_eq!(err, "1:1: error[E0001]: Unexpected { line: 1, col: 5 }\n");
+    }
+
+    #[test]
+    fn explain_known_code() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        assert!(run_explain("E0001", &mut out, &mut err).unwrap());
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("The input ended, or a character appeared"));
+        assert!(out.contains("print \"hello\";"));
+        assert!(err.is_empty());
+    }
+
+    #[test]
+    fn explain_unknown_code() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        assert!(!run_explain("E9999", &mut out, &mut err).unwrap());
+
+        assert!(out.is_empty());
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "no explanation for error code 'E9999'\n"
+        );
+    }
 }
@@ -0,0 +1,109 @@
+use crate::{Cli, run};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Output {
+    pub out: String,
+    pub err: String,
+}
+
+/// Read-eval-print loop state, independent of where lines come from
+pub struct Repl<'a> {
+    cli: &'a Cli,
+    color: bool,
+    finished: bool,
+}
+
+impl<'a> Repl<'a> {
+    const PROMPT: &'static str = "> ";
+    const QUIT: &'static str = "q!";
+
+    pub fn new(cli: &'a Cli, color: bool) -> Self {
+        Self {
+            cli,
+            color,
+            finished: false,
+        }
+    }
+
+    pub fn banner(&self) -> String {
+        let version = env!("CARGO_PKG_VERSION");
+        let name = env!("CARGO_PKG_NAME");
+        let authors = env!("CARGO_PKG_AUTHORS");
+        let quit = Self::QUIT;
+
+        format!("Welcome to {name} version {version} by {authors}\nUse {quit} to quit\n")
+    }
+
+    /// Prompt to show before reading the next line, `None` once the session is over
+    pub fn prompt(&self) -> Option<&'static str> {
+        (!self.finished).then_some(Self::PROMPT)
+    }
+
+    /// Feeds one line of input, or `None` at end of input
+    pub fn feed(&mut self, line: Option<&str>) -> std::io::Result<Output> {
+        let mut output = Output::default();
+        match line {
+            Some(line) if line.strip_suffix('\n').unwrap_or(line) != Self::QUIT => {
+                let mut out = Vec::new();
+                let mut err = Vec::new();
+                run(line, self.cli, self.color, &mut out, &mut err)?;
+                output.out = String::from_utf8_lossy(&out).into_owned();
+                output.err = String::from_utf8_lossy(&err).into_owned();
+            }
+            _ => self.finished = true,
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn session(cli: &Cli, lines: &[&str]) -> Vec<String> {
+        let mut repl = Repl::new(cli, false);
+        let mut transcript = vec![repl.banner()];
+        let mut lines = lines.iter();
+        while let Some(prompt) = repl.prompt() {
+            transcript.push(prompt.to_string());
+            let output = repl.feed(lines.next().copied()).unwrap();
+            if !output.out.is_empty() {
+                transcript.push(output.out);
+            }
+            if !output.err.is_empty() {
+                transcript.push(format!("stderr: {}", output.err));
+            }
+        }
+        transcript
+    }
+
+    #[test]
+    fn scripted_session() {
+        let cli = Cli::parse_from(["rlox"]);
+        let transcript = session(&cli, &["var a;\n", "\n", "#\n", "q!\n", "print a;\n"]);
+
+        assert_eq!(
+            transcript[1..],
+            [
+                "> ",
+                "Var\nIdent(\"a\")\nSemicolon\n",
+                "> ",
+                "> ",
+                "stderr: 1:1: error[E0002]: Unknown\n",
+                "> ",
+            ]
+        );
+        assert!(transcript[0].starts_with("Welcome to rlox"));
+    }
+
+    #[test]
+    fn end_of_input_finishes() {
+        let cli = Cli::parse_from(["rlox"]);
+        let transcript = session(&cli, &["nil"]);
+
+        assert_eq!(transcript[1..], ["> ", "Nil\n", "> "]);
+    }
+}